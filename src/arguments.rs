@@ -2,14 +2,8 @@
 //! Reads and interprets command-line arguments.
 
 use clap::{builder::PossibleValuesParser, Parser, Subcommand, ValueEnum};
-use core::time::Duration;
 use serde::Serialize;
 
-/// Poll rate in Hz
-static POLL_RATE: u64 = 100;
-/// Poll duration in ms
-pub static POLL_DURATION: Duration = Duration::from_millis(1000 / POLL_RATE / 2);
-
 /// Serial port session parity
 #[derive(ValueEnum, Clone, Copy, Default, Debug, Serialize)]
 #[serde(rename_all = "kebab-case")]
@@ -50,6 +44,29 @@ impl From<FlowControl> for serialport::FlowControl {
     }
 }
 
+/// Capture log format
+#[derive(ValueEnum, Clone, Copy, Default, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// Append bytes to the log file as-is
+    #[default]
+    Raw,
+    /// Prefix each chunk with the inter-chunk delay so it can be replayed
+    Timestamped,
+    /// One hex-encoded line per chunk
+    Hex,
+}
+
+/// Serial port read policy: whether a read returns as soon as any bytes
+/// have arrived, or waits to fill the buffer (or time out).
+#[derive(ValueEnum, Clone, Copy, Default, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadMode {
+    #[default]
+    Any,
+    All,
+}
+
 /// Serial port session task
 #[derive(Subcommand, Debug)]
 pub enum Task {
@@ -57,6 +74,8 @@ pub enum Task {
     Open { device: String },
     /// List available devices
     List,
+    /// Replay a timestamped capture to stdout
+    Replay { file: String },
 }
 
 /// Arguments
@@ -72,6 +91,10 @@ pub struct Arguments {
     #[arg(short, long, default_value_t = String::from("8"), value_parser = PossibleValuesParser::new(["5", "6", "7", "8"]))]
     pub data_bits: String,
 
+    /// Line stop bits
+    #[arg(long, default_value_t = String::from("1"), value_parser = PossibleValuesParser::new(["1", "2"]))]
+    pub stop_bits: String,
+
     /// Flow control
     #[arg(short, long, default_value_t, value_enum)]
     pub flow_control: FlowControl,
@@ -80,6 +103,40 @@ pub struct Arguments {
     #[arg(short, long, default_value_t, value_enum)]
     pub parity: Parity,
 
+    /// Directory where files received over ZMODEM are stored
+    #[arg(long, default_value_t = String::from("."))]
+    pub download_dir: String,
+
+    /// Tee session traffic to this log file
+    #[arg(long)]
+    pub log: Option<String>,
+
+    /// Capture log format
+    #[arg(long, default_value_t, value_enum)]
+    pub log_format: LogFormat,
+
+    /// Also capture outbound (typed/sent) bytes, not just inbound
+    #[arg(long)]
+    pub log_outbound: bool,
+
+    /// Path to a keymap config file (TOML); defaults to
+    /// `$HOME/.config/tior/config.toml`
+    #[arg(long)]
+    pub config: Option<String>,
+
+    /// Base read timeout, in milliseconds
+    #[arg(long, default_value_t = 5)]
+    pub read_timeout_ms: u64,
+
+    /// Additional read timeout per requested byte, in microseconds;
+    /// effective timeout = base + buffer length x this multiplier
+    #[arg(long, default_value_t = 0)]
+    pub read_timeout_multiplier_us: u64,
+
+    /// Read mode
+    #[arg(long, default_value_t, value_enum)]
+    pub read_mode: ReadMode,
+
     #[command(subcommand)]
     pub task: Task,
 }
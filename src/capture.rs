@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+//! Tees session traffic to a log file, keeping this layer separate from the
+//! `pretty_env_logger` debug output.
+
+use crate::arguments::LogFormat;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::time::Instant;
+
+/// Tees serial traffic to a log file in the configured format.
+pub struct Capture {
+    file: File,
+    format: LogFormat,
+    last: Instant,
+}
+
+impl Capture {
+    /// Open (creating if needed, appending otherwise) the log at `path`.
+    pub fn create(path: &str, format: LogFormat) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file,
+            format,
+            last: Instant::now(),
+        })
+    }
+
+    /// Append a chunk of session traffic to the log.
+    pub fn log(&mut self, buf: &[u8]) -> io::Result<()> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        match self.format {
+            LogFormat::Raw => self.file.write_all(buf)?,
+            LogFormat::Hex => writeln!(self.file, "{}", hex_encode(buf))?,
+            LogFormat::Timestamped => {
+                let delta_ms = self.last.elapsed().as_millis();
+                self.last = Instant::now();
+                writeln!(self.file, "+{delta_ms}ms {}", hex_encode(buf))?;
+            }
+        }
+
+        self.file.flush()
+    }
+}
+
+fn hex_encode(buf: &[u8]) -> String {
+    buf.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode(s: &str) -> io::Result<Vec<u8>> {
+    if !s.is_ascii() || s.len() % 2 != 0 {
+        return Err(io::Error::from(io::ErrorKind::InvalidData));
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&s[i..i + 2], 16)
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))
+        })
+        .collect()
+}
+
+/// Replay a `LogFormat::Timestamped` capture to `out`, sleeping between
+/// chunks for the recorded inter-chunk delay.
+pub fn replay(path: &str, out: &mut impl Write) -> io::Result<()> {
+    let contents = std::fs::read_to_string(path)?;
+
+    for line in contents.lines() {
+        let Some((delay, hex)) = line.split_once(' ') else {
+            continue;
+        };
+        let Some(ms) = delay.strip_prefix('+').and_then(|s| s.strip_suffix("ms")) else {
+            continue;
+        };
+        let Ok(ms) = ms.parse::<u64>() else {
+            continue;
+        };
+
+        std::thread::sleep(std::time::Duration::from_millis(ms));
+        out.write_all(&hex_decode(hex)?)?;
+        out.flush()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+//! Runtime-configurable keymap, loaded from a TOML config file.
+//!
+//! Replaces the hardcoded `match key.code` tables in `Terminal` with a
+//! lookup from `(KeyCode, KeyModifiers)` to an `Action`, so escape
+//! sequences and command-mode letters can be remapped without recompiling.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// An action a key can be bound to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    EnterCommand,
+    Quit,
+    SendFile,
+    ReceiveFile,
+    SendBreak,
+    ToggleDtr,
+    ToggleRts,
+    /// Transmit this byte sequence verbatim.
+    Bytes(Vec<u8>),
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct KeymapFile {
+    #[serde(default)]
+    input: HashMap<String, Action>,
+    #[serde(default)]
+    command: HashMap<String, Action>,
+}
+
+/// Maps key events to actions, independently for the `WaitingInput` and
+/// `WaitingCommand` states.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    input: HashMap<(KeyCode, KeyModifiers), Action>,
+    command: HashMap<(KeyCode, KeyModifiers), Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut input = HashMap::new();
+        input.insert(
+            (KeyCode::Char('t'), KeyModifiers::CONTROL),
+            Action::EnterCommand,
+        );
+        for (code, bytes) in [
+            (KeyCode::Up, &[27, 91, 65][..]),
+            (KeyCode::Down, &[27, 91, 66]),
+            (KeyCode::Right, &[27, 91, 67]),
+            (KeyCode::Left, &[27, 91, 68]),
+            (KeyCode::End, &[27, 91, 70]),
+            (KeyCode::Home, &[27, 91, 72]),
+            (KeyCode::BackTab, &[27, 91, 90]),
+            (KeyCode::Insert, &[27, 91, 50, 126]),
+            (KeyCode::Delete, &[27, 91, 51, 126]),
+            (KeyCode::PageUp, &[27, 91, 53, 126]),
+            (KeyCode::PageDown, &[27, 91, 54, 126]),
+        ] {
+            input.insert((code, KeyModifiers::NONE), Action::Bytes(bytes.to_vec()));
+        }
+
+        let mut command = HashMap::new();
+        command.insert((KeyCode::Char('q'), KeyModifiers::NONE), Action::Quit);
+        command.insert((KeyCode::Char('s'), KeyModifiers::NONE), Action::SendFile);
+        command.insert(
+            (KeyCode::Char('r'), KeyModifiers::NONE),
+            Action::ReceiveFile,
+        );
+        command.insert((KeyCode::Char('b'), KeyModifiers::NONE), Action::SendBreak);
+        command.insert((KeyCode::Char('d'), KeyModifiers::NONE), Action::ToggleDtr);
+        command.insert((KeyCode::Char('g'), KeyModifiers::NONE), Action::ToggleRts);
+
+        Self { input, command }
+    }
+}
+
+impl Keymap {
+    /// Load the keymap for `--config <path>`, or the default config path if
+    /// none was given, falling back to the built-in table if neither exists.
+    pub fn load_from_args(config: &Option<String>) -> io::Result<Self> {
+        match config {
+            Some(path) => Self::load(Path::new(path)),
+            None => match default_config_path() {
+                Some(path) => Self::load(&path),
+                None => Ok(Self::default()),
+            },
+        }
+    }
+
+    /// Load a keymap from a TOML config file, overlaying it on top of the
+    /// built-in defaults. Missing files fall back to the defaults as-is.
+    fn load(path: &Path) -> io::Result<Self> {
+        let mut keymap = Self::default();
+
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(keymap),
+            Err(e) => return Err(e),
+        };
+
+        let file: KeymapFile =
+            toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        for (spec, action) in file.input {
+            match parse_key(&spec) {
+                Some(key) => {
+                    keymap.input.insert(key, action);
+                }
+                None => log::warn!("keymap: unrecognized key {spec:?} in [input]"),
+            }
+        }
+        for (spec, action) in file.command {
+            match parse_key(&spec) {
+                Some(key) => {
+                    keymap.command.insert(key, action);
+                }
+                None => log::warn!("keymap: unrecognized key {spec:?} in [command]"),
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    /// Look up the action bound to `key` while waiting for terminal input.
+    pub fn input_action(&self, key: &KeyEvent) -> Option<&Action> {
+        self.input.get(&(key.code, key.modifiers))
+    }
+
+    /// Look up the action bound to `key` while waiting for a command.
+    pub fn command_action(&self, key: &KeyEvent) -> Option<&Action> {
+        self.command.get(&(key.code, key.modifiers))
+    }
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| Path::new(&home).join(".config/tior/config.toml"))
+}
+
+/// Parse a key binding like `"ctrl+t"`, `"q"`, or `"up"`.
+fn parse_key(spec: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let (modifier_parts, key_part) = spec.rsplit_once('+').map_or(("", spec), |(m, k)| (m, k));
+
+    let mut modifiers = KeyModifiers::NONE;
+    if !modifier_parts.is_empty() {
+        for part in modifier_parts.split('+') {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+        }
+    }
+
+    let code = match key_part.to_ascii_lowercase().as_str() {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "insert" => KeyCode::Insert,
+        "delete" => KeyCode::Delete,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "backspace" => KeyCode::Backspace,
+        "enter" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        s if s.chars().count() == 1 => KeyCode::Char(s.chars().next().unwrap()),
+        _ => return None,
+    };
+
+    Some((code, modifiers))
+}
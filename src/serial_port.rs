@@ -1,17 +1,28 @@
 // SPDX-License-Identifier: GPL-2.0-or-later
 //! A serial port interface.
 
-use crate::arguments::{Arguments, POLL_DURATION};
+use crate::arguments::{Arguments, ReadMode};
 use std::io::{self, ErrorKind};
+use std::time::{Duration, Instant};
 
 /// A serial port connector.
-pub struct SerialPort(Box<dyn serialport::SerialPort>);
+pub struct SerialPort {
+    inner: Box<dyn serialport::SerialPort>,
+    /// Read timeout for a zero-length buffer.
+    base_timeout: Duration,
+    /// Additional read timeout per requested byte.
+    per_byte_timeout: Duration,
+    read_mode: ReadMode,
+}
 
 impl SerialPort {
     /// Connect to a serial port.
     pub fn new(device: String, args: &Arguments) -> io::Result<Self> {
-        let mut port = serialport::new(device, args.baud_rate)
-            .timeout(POLL_DURATION)
+        let base_timeout = Duration::from_millis(args.read_timeout_ms);
+        let per_byte_timeout = Duration::from_micros(args.read_timeout_multiplier_us);
+
+        let mut inner = serialport::new(device, args.baud_rate)
+            .timeout(base_timeout)
             .open()?;
 
         let data_bits = match args.data_bits.as_str() {
@@ -22,21 +33,66 @@ impl SerialPort {
             _ => return Err(io::Error::from(ErrorKind::InvalidInput)),
         };
 
-        port.set_data_bits(data_bits)?;
-        port.set_stop_bits(serialport::StopBits::One)?;
-        port.set_baud_rate(args.baud_rate)?;
-        port.set_parity(args.parity.into())?;
-        port.set_flow_control(args.flow_control.into())?;
+        let stop_bits = match args.stop_bits.as_str() {
+            "1" => serialport::StopBits::One,
+            "2" => serialport::StopBits::Two,
+            _ => return Err(io::Error::from(ErrorKind::InvalidInput)),
+        };
+
+        inner.set_data_bits(data_bits)?;
+        inner.set_stop_bits(stop_bits)?;
+        inner.set_baud_rate(args.baud_rate)?;
+        inner.set_parity(args.parity.into())?;
+        inner.set_flow_control(args.flow_control.into())?;
 
-        Ok(Self(port))
+        Ok(Self {
+            inner,
+            base_timeout,
+            per_byte_timeout,
+            read_mode: args.read_mode,
+        })
     }
-}
 
-impl io::Read for SerialPort {
-    /// Read data from the serial port. Returns zero length for the buffer,
-    /// if the operation expires.
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.0.read(buf).or_else(|e| {
+    /// Clone the underlying handle so the read and write sides of the port
+    /// can be driven from separate threads.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            inner: self.inner.try_clone()?,
+            base_timeout: self.base_timeout,
+            per_byte_timeout: self.per_byte_timeout,
+            read_mode: self.read_mode,
+        })
+    }
+
+    /// Assert or clear the DTR (Data Terminal Ready) line.
+    pub fn set_dtr(&mut self, on: bool) -> io::Result<()> {
+        self.inner.write_data_terminal_ready(on)
+    }
+
+    /// Assert or clear the RTS (Request To Send) line.
+    pub fn set_rts(&mut self, on: bool) -> io::Result<()> {
+        self.inner.write_request_to_send(on)
+    }
+
+    /// Send a serial BREAK: hold the line in the break state briefly, then
+    /// release it. Many embedded targets use this (or a DTR toggle) to
+    /// enter a bootloader.
+    pub fn send_break(&mut self) -> io::Result<()> {
+        self.inner.set_break()?;
+        std::thread::sleep(Duration::from_millis(250));
+        self.inner.clear_break()
+    }
+
+    /// The read timeout for a buffer of `buf_len` bytes: `base +
+    /// buf_len * per_byte_timeout`.
+    fn effective_timeout(&self, buf_len: usize) -> Duration {
+        self.base_timeout + self.per_byte_timeout * buf_len as u32
+    }
+
+    /// Read once, returning as soon as any bytes are available. Returns
+    /// zero length if the read times out before any bytes arrive.
+    fn read_any(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf).or_else(|e| {
             if e.kind() == ErrorKind::TimedOut {
                 Ok(0)
             } else {
@@ -44,13 +100,55 @@ impl io::Read for SerialPort {
             }
         })
     }
+
+    /// Read repeatedly until `buf` is full or `deadline` passes, returning
+    /// whatever was collected either way.
+    fn read_all(&mut self, buf: &mut [u8], deadline: Instant) -> io::Result<usize> {
+        let mut filled = 0;
+
+        while filled < buf.len() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            self.inner.set_timeout(remaining)?;
+
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::TimedOut => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(filled)
+    }
+}
+
+impl io::Read for SerialPort {
+    /// Read data from the serial port, honoring the configured read mode
+    /// and timeout model. In `Any` mode, returns zero length for the buffer
+    /// if the operation expires before any bytes arrive. In `All` mode,
+    /// returns however many bytes arrived before the operation expired,
+    /// which may be fewer than requested or zero.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let timeout = self.effective_timeout(buf.len());
+
+        match self.read_mode {
+            ReadMode::Any => {
+                self.inner.set_timeout(timeout)?;
+                self.read_any(buf)
+            }
+            ReadMode::All => self.read_all(buf, Instant::now() + timeout),
+        }
+    }
 }
 
 impl io::Write for SerialPort {
     /// Write data to the serial port. Returns zero length for the buffer,
     /// if the operation expires.
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.write(buf).or_else(|e| {
+        self.inner.write(buf).or_else(|e| {
             if e.kind() == ErrorKind::TimedOut {
                 Ok(0)
             } else {
@@ -61,6 +159,6 @@ impl io::Write for SerialPort {
 
     // Flush the intermediate buffer.
     fn flush(&mut self) -> io::Result<()> {
-        self.0.flush()
+        self.inner.flush()
     }
 }
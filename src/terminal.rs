@@ -2,16 +2,126 @@
 //! A command-line interface (CLI) driven by a finite-state machine
 
 use crate::arguments::Arguments;
+use crate::capture::{self, Capture};
+use crate::keymap::{Action, Keymap};
 use crate::path_complete::PathComplete;
 use crate::serial_port::SerialPort;
+use crate::zmodem;
 use mode::{Entry, Mode, ReceivingFile, SendingFile, WaitingCommand, WaitingInput};
+use std::path::PathBuf;
 
 use crossterm::{
+    cursor,
     event::{self, Event, KeyCode, KeyModifiers},
     execute,
-    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+    style::Print,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::io::{self, Read, Write};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+/// Shared handle to the optional session capture log.
+type SharedCapture = Arc<Mutex<Capture>>;
+
+/// Tracks the modem control lines so they can be toggled and surfaced in
+/// the status line. `None` means we have not driven that line ourselves yet,
+/// so its power-on state is unknown: `serialport` lets us write DTR/RTS but
+/// doesn't expose a way to read back what the port asserted on open.
+#[derive(Default)]
+struct LineState {
+    dtr: Option<bool>,
+    rts: Option<bool>,
+}
+
+/// Redraw the top status line with the current modem control line state,
+/// then restore the cursor to wherever it was.
+fn render_status(line_state: &LineState) -> io::Result<()> {
+    fn label(state: Option<bool>) -> &'static str {
+        match state {
+            Some(true) => "on",
+            Some(false) => "off",
+            None => "unknown",
+        }
+    }
+
+    execute!(
+        io::stdout(),
+        cursor::SavePosition,
+        cursor::MoveTo(0, 0),
+        Clear(ClearType::CurrentLine),
+        Print(format!(
+            "DTR:{} RTS:{}",
+            label(line_state.dtr),
+            label(line_state.rts),
+        )),
+        cursor::RestorePosition,
+    )
+}
+
+/// Messages sent from the main thread to the reader thread.
+enum ReaderControl {
+    /// Stop forwarding bytes to stdout; a transfer state now owns the line.
+    Pause,
+    /// Resume forwarding bytes to stdout.
+    Resume,
+    Shutdown,
+}
+
+/// Messages sent from the reader thread back to the main thread.
+enum ReaderEvent {
+    /// Acknowledges that the reader has stopped reading and it is safe for
+    /// the main thread to read from its own handle (e.g. for ZMODEM).
+    Paused,
+    Error(io::Error),
+}
+
+/// Continuously read from `port` and echo decoded bytes to stdout, until
+/// told to shut down. While paused, the port is left untouched so another
+/// owner (a ZMODEM transfer) can read it without the two handles racing.
+fn reader_loop(
+    mut port: SerialPort,
+    control_rx: mpsc::Receiver<ReaderControl>,
+    event_tx: mpsc::Sender<ReaderEvent>,
+    capture: Option<SharedCapture>,
+) {
+    let mut buf = [0u8; 512];
+
+    loop {
+        match control_rx.try_recv() {
+            Ok(ReaderControl::Pause) => {
+                let _ = event_tx.send(ReaderEvent::Paused);
+                match control_rx.recv() {
+                    Ok(ReaderControl::Resume) => continue,
+                    _ => return,
+                }
+            }
+            Ok(ReaderControl::Resume) => continue,
+            Ok(ReaderControl::Shutdown) | Err(mpsc::TryRecvError::Disconnected) => return,
+            Err(mpsc::TryRecvError::Empty) => {}
+        }
+
+        match port.read(&mut buf) {
+            Ok(0) => continue,
+            Ok(n) => {
+                if let Some(capture) = &capture {
+                    if let Ok(mut capture) = capture.lock() {
+                        let _ = capture.log(&buf[..n]);
+                    }
+                }
+
+                let flushed = io::stdout().write_all(&buf[..n]).and_then(|_| io::stdout().flush());
+                if flushed.is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                let _ = event_tx.send(ReaderEvent::Error(e));
+                return;
+            }
+        }
+    }
+}
 
 fsmentry::dsl! {
     #[derive(Debug)]
@@ -27,27 +137,104 @@ pub struct Terminal;
 
 impl Terminal {
     pub fn run(&self, args: &Arguments, device: &str) -> io::Result<()> {
-        let mut port = SerialPort::new(device.to_string(), args)?;
-        let mut mode = Mode::new(mode::State::WaitingInput);
-        let mut buf: [u8; 512] = [0; 512];
+        let mut write_port = SerialPort::new(device.to_string(), args)?;
+        let read_port = write_port.try_clone()?;
+
+        let capture = args
+            .log
+            .as_ref()
+            .map(|path| Capture::create(path, args.log_format))
+            .transpose()?
+            .map(|capture| Arc::new(Mutex::new(capture)));
+
+        let keymap = Keymap::load_from_args(&args.config)?;
+
+        let (control_tx, control_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::channel();
+        let reader_capture = capture.clone();
+        let reader = thread::spawn(move || reader_loop(read_port, control_rx, event_tx, reader_capture));
 
         terminal::enable_raw_mode()?;
         execute!(io::stdout(), EnterAlternateScreen)?;
 
-        loop {
-            let size = port.read(&mut buf)?;
+        let mut line_state = LineState::default();
+        render_status(&line_state)?;
 
-            io::stdout().write_all(&buf[..size])?;
-            io::stdout().flush()?;
+        let mut mode = Mode::new(mode::State::WaitingInput);
+        let result = loop {
+            let step = match mode.entry() {
+                Entry::WaitingInput(it) => self.visit_waiting_input(
+                    it,
+                    &mut write_port,
+                    capture.as_ref(),
+                    args.log_outbound,
+                    &keymap,
+                ),
+                Entry::WaitingCommand(it) => {
+                    self.visit_waiting_command(it, &mut write_port, &keymap, &mut line_state)
+                }
+                Entry::SendingFile(it) => {
+                    self.with_reader_paused(&control_tx, &event_rx, |port| {
+                        self.visit_sending_file(it, port)
+                    }, &mut write_port)
+                }
+                Entry::ReceivingFile(it) => {
+                    self.with_reader_paused(&control_tx, &event_rx, |port| {
+                        self.visit_receiving_file(it, port, &args.download_dir)
+                    }, &mut write_port)
+                }
+                Entry::Exit => break Ok(()),
+            };
+
+            if let Err(e) = step {
+                break Err(e);
+            }
+
+            if let Ok(ReaderEvent::Error(e)) = event_rx.try_recv() {
+                break Err(e);
+            }
+        };
 
-            match mode.entry() {
-                Entry::WaitingInput(it) => self.visit_waiting_input(it, &mut port),
-                Entry::WaitingCommand(it) => self.visit_waiting_command(it, &mut port),
-                Entry::SendingFile(it) => self.visit_sending_file(it, &mut port),
-                Entry::ReceivingFile(it) => self.visit_receiving_file(it, &mut port),
-                Entry::Exit => return Ok(()),
-            }?;
+        let _ = control_tx.send(ReaderControl::Shutdown);
+        let _ = reader.join();
+
+        result
+    }
+
+    /// Pause the reader thread, run `f` with exclusive access to the port,
+    /// then resume. Used by the transfer states so the reader and the
+    /// ZMODEM state machine never read the line at the same time. Blocks
+    /// until the reader actually acknowledges the pause rather than racing
+    /// a fixed timeout against the reader's own (user-configurable) read
+    /// timeout, since the reader only notices `Pause` between reads.
+    fn with_reader_paused(
+        &self,
+        control_tx: &mpsc::Sender<ReaderControl>,
+        event_rx: &mpsc::Receiver<ReaderEvent>,
+        f: impl FnOnce(&mut SerialPort) -> io::Result<()>,
+        port: &mut SerialPort,
+    ) -> io::Result<()> {
+        let _ = control_tx.send(ReaderControl::Pause);
+        match event_rx.recv() {
+            Ok(ReaderEvent::Paused) => {}
+            Ok(ReaderEvent::Error(e)) => return Err(e),
+            Err(_) => {} // Reader thread is gone; nothing left to race with.
         }
+
+        let result = f(port);
+
+        let _ = control_tx.send(ReaderControl::Resume);
+        result
+    }
+
+    /// Re-emit a timestamped capture to stdout, honoring its recorded
+    /// inter-chunk delays, using the same alternate-screen/raw-mode setup
+    /// as a live session.
+    pub fn replay(&self, file: &str) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+
+        capture::replay(file, &mut io::stdout())
     }
 
     pub fn available_ports(&self) -> io::Result<Vec<String>> {
@@ -63,59 +250,87 @@ impl Terminal {
         execute!(io::stdout(), LeaveAlternateScreen)
     }
 
-    fn visit_waiting_input(&self, it: WaitingInput, port: &mut SerialPort) -> io::Result<()> {
-        match event::read()? {
-            Event::Key(ref key) if key.modifiers == KeyModifiers::NONE => {
-                // The buffer is sized to fit any UTF-8 character (max 4 bytes):
-                let mut buf: [u8; 4] = [0; 4];
-
-                // TODO: Substitute later on with a hash table with `KeyEvent`
-                // as the lookup, thus allowing run-time configuration.
-                let encoded = match key.code {
-                    // UTF-8:
-                    KeyCode::Char(ch) => ch.encode_utf8(&mut buf).as_bytes(),
-                    KeyCode::Backspace => &[8],
-                    KeyCode::Tab => &[9],
-                    KeyCode::Enter => &[10],
-                    KeyCode::Esc => &[27],
-                    // Escape:
-                    KeyCode::Up => &[27, 91, 65],
-                    KeyCode::Down => &[27, 91, 66],
-                    KeyCode::Right => &[27, 91, 67],
-                    KeyCode::Left => &[27, 91, 68],
-                    KeyCode::End => &[27, 91, 70],
-                    KeyCode::Home => &[27, 91, 72],
-                    KeyCode::BackTab => &[27, 91, 90],
-                    KeyCode::Insert => &[27, 91, 50, 126],
-                    KeyCode::Delete => &[27, 91, 51, 126],
-                    KeyCode::PageUp => &[27, 91, 53, 126],
-                    KeyCode::PageDown => &[27, 91, 54, 126],
-                    _ => &[],
-                };
-
-                if !encoded.is_empty() {
-                    port.write_all(encoded)?;
+    fn visit_waiting_input(
+        &self,
+        it: WaitingInput,
+        port: &mut SerialPort,
+        capture: Option<&SharedCapture>,
+        log_outbound: bool,
+        keymap: &Keymap,
+    ) -> io::Result<()> {
+        let write_and_log = |port: &mut SerialPort, bytes: &[u8]| -> io::Result<()> {
+            port.write_all(bytes)?;
+            if log_outbound {
+                if let Some(capture) = capture {
+                    if let Ok(mut capture) = capture.lock() {
+                        let _ = capture.log(bytes);
+                    }
                 }
             }
-            Event::Key(ref key)
-                if key.code == KeyCode::Char('t') && key.modifiers == KeyModifiers::CONTROL =>
-            {
-                it.waiting_command();
-            }
+            Ok(())
+        };
+
+        match event::read()? {
+            Event::Key(ref key) => match keymap.input_action(key) {
+                Some(Action::EnterCommand) => it.waiting_command(),
+                Some(Action::Bytes(bytes)) => write_and_log(port, bytes)?,
+                Some(_) => log::trace!("action not valid while waiting for input: {key:?}"),
+                None if key.modifiers == KeyModifiers::NONE => {
+                    // The buffer is sized to fit any UTF-8 character (max 4 bytes):
+                    let mut buf: [u8; 4] = [0; 4];
+                    let encoded: &[u8] = match key.code {
+                        KeyCode::Char(ch) => ch.encode_utf8(&mut buf).as_bytes(),
+                        KeyCode::Backspace => &[8],
+                        KeyCode::Tab => &[9],
+                        KeyCode::Enter => &[10],
+                        KeyCode::Esc => &[27],
+                        _ => &[],
+                    };
+
+                    if !encoded.is_empty() {
+                        write_and_log(port, encoded)?;
+                    }
+                }
+                None => log::trace!("unbound key: {key:?}"),
+            },
             event => log::trace!("unhandled: {event:?}"),
         }
         Ok(())
     }
 
-    fn visit_waiting_command(&self, it: WaitingCommand, _: &mut SerialPort) -> io::Result<()> {
+    fn visit_waiting_command(
+        &self,
+        it: WaitingCommand,
+        port: &mut SerialPort,
+        keymap: &Keymap,
+        line_state: &mut LineState,
+    ) -> io::Result<()> {
         match event::read()? {
             Event::Key(ref key) if key.modifiers == KeyModifiers::NONE => {
-                // TODO: Substitute later on with a hash table with `KeyEvent`
-                // as the lookup, thus allowing run-time configuration.
-                match key.code {
-                    KeyCode::Char('q') => it.exit(),
-                    KeyCode::Char('s') => it.sending_file(),
-                    KeyCode::Char('r') => it.receiving_file(),
+                match keymap.command_action(key) {
+                    Some(Action::Quit) => it.exit(),
+                    Some(Action::SendFile) => it.sending_file(),
+                    Some(Action::ReceiveFile) => it.receiving_file(),
+                    Some(Action::SendBreak) => {
+                        port.send_break()?;
+                        it.waiting_input();
+                    }
+                    Some(Action::ToggleDtr) => {
+                        // We don't know the port's power-on state, so an
+                        // unknown line is assumed asserted and toggled off.
+                        let next = !line_state.dtr.unwrap_or(true);
+                        port.set_dtr(next)?;
+                        line_state.dtr = Some(next);
+                        render_status(line_state)?;
+                        it.waiting_input();
+                    }
+                    Some(Action::ToggleRts) => {
+                        let next = !line_state.rts.unwrap_or(true);
+                        port.set_rts(next)?;
+                        line_state.rts = Some(next);
+                        render_status(line_state)?;
+                        it.waiting_input();
+                    }
                     _ => it.waiting_input(),
                 }
             }
@@ -124,7 +339,7 @@ impl Terminal {
         Ok(())
     }
 
-    fn visit_sending_file(&self, it: SendingFile, _: &mut SerialPort) -> io::Result<()> {
+    fn visit_sending_file(&self, it: SendingFile, port: &mut SerialPort) -> io::Result<()> {
         let current_dir = std::env::current_dir()?;
         let help_message = format!("PWD: {}", current_dir.to_string_lossy());
         let path = inquire::Text::new("Send")
@@ -133,15 +348,28 @@ impl Terminal {
             .prompt()
             .unwrap_or_default();
 
-        log::debug!("send: {path}");
+        if !path.is_empty() {
+            log::debug!("send: {path}");
+            if let Err(e) = zmodem::send_file(port, std::path::Path::new(&path)) {
+                log::error!("zmodem send: {e}");
+            }
+        }
 
-        // TODO: zmodem
         it.waiting_input();
         Ok(())
     }
 
-    fn visit_receiving_file(&self, it: ReceivingFile, _: &mut SerialPort) -> io::Result<()> {
-        // TODO: zmodem
+    fn visit_receiving_file(
+        &self,
+        it: ReceivingFile,
+        port: &mut SerialPort,
+        download_dir: &str,
+    ) -> io::Result<()> {
+        log::debug!("receive: into {download_dir}");
+        if let Err(e) = zmodem::receive_file(port, &PathBuf::from(download_dir)) {
+            log::error!("zmodem receive: {e}");
+        }
+
         it.waiting_input();
         Ok(())
     }
@@ -0,0 +1,361 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+//! A small ZMODEM sender/receiver used by the `SendingFile`/`ReceivingFile`
+//! terminal states.
+//!
+//! This only implements the subset of the protocol `tior` needs to move a
+//! single file over the line: hex headers (format `B`), binary CRC32 data
+//! subpackets (format `C`), and the ZRQINIT/ZRINIT/ZFILE/ZDATA/ZEOF/ZFIN
+//! handshake. Crash recovery (`ZRPOS` resume) and multi-file batches are out
+//! of scope.
+
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const ZPAD: u8 = b'*';
+const ZDLE: u8 = 0x18;
+const ZDLEE: u8 = 0x58;
+const ZBIN: u8 = b'A';
+const ZHEX: u8 = b'B';
+const ZBIN32: u8 = b'C';
+
+const ZCRCE: u8 = b'h';
+const ZCRCG: u8 = b'i';
+const ZCRCQ: u8 = b'j';
+const ZCRCW: u8 = b'k';
+
+/// ZMODEM header frame types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum FrameType {
+    ZRqinit = 0,
+    ZRinit = 1,
+    ZAck = 3,
+    ZFile = 4,
+    ZSkip = 5,
+    ZNak = 6,
+    ZAbort = 7,
+    ZFin = 8,
+    ZRpos = 9,
+    ZData = 10,
+    ZEof = 11,
+}
+
+impl FrameType {
+    fn from_byte(b: u8) -> Option<Self> {
+        Some(match b {
+            0 => Self::ZRqinit,
+            1 => Self::ZRinit,
+            3 => Self::ZAck,
+            4 => Self::ZFile,
+            5 => Self::ZSkip,
+            6 => Self::ZNak,
+            7 => Self::ZAbort,
+            8 => Self::ZFin,
+            9 => Self::ZRpos,
+            10 => Self::ZData,
+            11 => Self::ZEof,
+            _ => return None,
+        })
+    }
+}
+
+/// Data subpacket size. Every subpacket but the last ends with `ZCRCW` and
+/// blocks for the receiver's `ZACK` before the next is sent, so the transfer
+/// gets basic flow control instead of streaming blind.
+const SUBPACKET_LEN: usize = 1024;
+
+fn crc16_update(crc: u16, byte: u8) -> u16 {
+    let mut crc = crc ^ ((byte as u16) << 8);
+    for _ in 0..8 {
+        crc = if crc & 0x8000 != 0 {
+            (crc << 1) ^ 0x1021
+        } else {
+            crc << 1
+        };
+    }
+    crc
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, &b| crc16_update(crc, b))
+}
+
+fn crc32_update(crc: u32, byte: u8) -> u32 {
+    let mut crc = crc ^ byte as u32;
+    for _ in 0..8 {
+        crc = if crc & 1 != 0 {
+            (crc >> 1) ^ 0xEDB8_8320
+        } else {
+            crc >> 1
+        };
+    }
+    crc
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| crc32_update(crc, b))
+}
+
+/// Escape control bytes (ZDLE, DLE, XON/XOFF, CR, CR with the high bit set,
+/// `@`, DEL) as `ZDLE (orig ^ 0x40)`.
+fn put_escaped<W: Write>(w: &mut W, byte: u8) -> io::Result<()> {
+    match byte {
+        ZDLE | 0x10 | 0x11 | 0x13 | 0x0D | 0x8D | 0x40 | 0x7F => {
+            w.write_all(&[ZDLE, byte ^ 0x40])
+        }
+        _ => w.write_all(&[byte]),
+    }
+}
+
+/// Send a hex (format `B`) header: `ZPAD ZPAD ZDLE 'B' <type> <4 pos bytes, hex> <crc16, hex> CR LF`.
+fn send_hex_header<W: Write>(w: &mut W, frame: FrameType, pos: u32) -> io::Result<()> {
+    let mut body = vec![frame as u8];
+    body.extend_from_slice(&pos.to_le_bytes());
+    let crc = crc16(&body);
+
+    w.write_all(&[ZPAD, ZPAD, ZDLE, ZHEX])?;
+    for byte in body.iter().chain(crc.to_be_bytes().iter()) {
+        write!(w, "{byte:02x}")?;
+    }
+    w.write_all(b"\r\n")?;
+    w.flush()
+}
+
+/// Send a binary CRC32 (format `C`) data subpacket, escaping as we go.
+fn send_data_subpacket<W: Write>(w: &mut W, data: &[u8], end: u8) -> io::Result<()> {
+    for &byte in data {
+        put_escaped(w, byte)?;
+    }
+    w.write_all(&[ZDLE, end])?;
+
+    let mut crc_input = data.to_vec();
+    crc_input.push(end);
+    let crc = crc32(&crc_input);
+    for byte in crc.to_le_bytes() {
+        put_escaped(w, byte)?;
+    }
+    w.flush()
+}
+
+/// Read a single byte from `r`, retrying until `deadline` passes. `SerialPort`
+/// (and any other timeout-based reader) returns `Ok(0)` on a benign read
+/// timeout rather than blocking forever or signalling EOF, so we poll rather
+/// than use `Read::read_exact`, which treats `Ok(0)` as end-of-stream.
+fn read_byte<R: Read>(r: &mut R, deadline: Instant) -> io::Result<u8> {
+    let mut byte = [0u8; 1];
+    loop {
+        match r.read(&mut byte) {
+            Ok(1) => return Ok(byte[0]),
+            Ok(_) => {
+                if Instant::now() > deadline {
+                    return Err(io::Error::new(ErrorKind::TimedOut, "zmodem: read timed out"));
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Read one byte, un-escaping ZDLE sequences transparently.
+fn read_unescaped<R: Read>(r: &mut R, deadline: Instant) -> io::Result<u8> {
+    let byte = read_byte(r, deadline)?;
+    if byte != ZDLE {
+        return Ok(byte);
+    }
+    Ok(read_byte(r, deadline)? ^ 0x40)
+}
+
+/// Decode a single ASCII hex digit, without requiring the surrounding bytes
+/// to form valid UTF-8 (arbitrary noise on the line need not be a valid
+/// `char` boundary, so we stay in raw bytes rather than going through `str`).
+fn hex_digit(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decode a two-digit ASCII hex byte pair, e.g. `(b'2', b'a')` -> `0x2a`.
+fn hex_pair_to_byte(hi: u8, lo: u8) -> Option<u8> {
+    Some(hex_digit(hi)? << 4 | hex_digit(lo)?)
+}
+
+/// Read back a hex header, returning its frame type and position field.
+/// Non-header noise on the line (the remote's local echo, stray bytes) is
+/// skipped until ZPAD/ZDLE is seen.
+fn recv_hex_header<R: Read>(r: &mut R, timeout: Duration) -> io::Result<(FrameType, u32)> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        if read_byte(r, deadline)? != ZDLE {
+            continue;
+        }
+        if read_byte(r, deadline)? != ZHEX {
+            continue;
+        }
+        break;
+    }
+
+    let mut hex = [0u8; 14];
+    for slot in hex.iter_mut() {
+        *slot = read_byte(r, deadline)?;
+    }
+    let mut body = [0u8; 7];
+    for (i, pair) in body.iter_mut().zip(hex.chunks_exact(2)) {
+        *i = hex_pair_to_byte(pair[0], pair[1]).ok_or_else(|| io::Error::from(ErrorKind::InvalidData))?;
+    }
+
+    if crc16(&body[..5]) != u16::from_be_bytes([body[5], body[6]]) {
+        return Err(io::Error::new(ErrorKind::InvalidData, "zmodem: bad header crc16"));
+    }
+
+    let frame = FrameType::from_byte(body[0]).ok_or_else(|| io::Error::from(ErrorKind::InvalidData))?;
+    let pos = u32::from_le_bytes([body[1], body[2], body[3], body[4]]);
+
+    // Trailing CR LF.
+    let _ = read_byte(r, deadline);
+    let _ = read_byte(r, deadline);
+
+    Ok((frame, pos))
+}
+
+/// Read a binary CRC32 data subpacket, returning its payload and its
+/// frame-end byte (`ZCRCE`, `ZCRCG`, `ZCRCQ` or `ZCRCW`) for the caller to
+/// act on.
+fn recv_data_subpacket<R: Read>(r: &mut R, timeout: Duration) -> io::Result<(Vec<u8>, u8)> {
+    let deadline = Instant::now() + timeout;
+    let mut data = Vec::new();
+    loop {
+        let b = read_byte(r, deadline)?;
+        if b == ZDLE {
+            let b = read_byte(r, deadline)?;
+            match b {
+                ZCRCE | ZCRCG | ZCRCQ | ZCRCW => {
+                    let end = b;
+                    let mut crc_bytes = [0u8; 4];
+                    for slot in crc_bytes.iter_mut() {
+                        *slot = read_unescaped(r, deadline)?;
+                    }
+                    let mut crc_input = data.clone();
+                    crc_input.push(end);
+                    if crc32(&crc_input) != u32::from_le_bytes(crc_bytes) {
+                        return Err(io::Error::new(ErrorKind::InvalidData, "zmodem: bad crc32"));
+                    }
+                    return Ok((data, end));
+                }
+                other => data.push(other ^ 0x40),
+            }
+        } else {
+            data.push(b);
+        }
+    }
+}
+
+/// Drive the sender side of a ZMODEM transfer: wait for `ZRINIT`, announce
+/// the file with `ZFILE`, then stream it as `ZDATA` subpackets.
+pub fn send_file<S: Read + Write>(port: &mut S, path: &Path) -> io::Result<()> {
+    let data = fs::read(path)?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    send_hex_header(port, FrameType::ZRqinit, 0)?;
+    let (frame, _) = recv_hex_header(port, Duration::from_secs(10))?;
+    if frame != FrameType::ZRinit {
+        return Err(io::Error::new(ErrorKind::Other, "zmodem: expected ZRINIT"));
+    }
+
+    send_hex_header(port, FrameType::ZFile, 0)?;
+    let subheader = format!("{name}\0{} 0\0", data.len());
+    send_data_subpacket(port, subheader.as_bytes(), ZCRCW)?;
+    let (frame, _) = recv_hex_header(port, Duration::from_secs(10))?;
+    if frame != FrameType::ZRpos && frame != FrameType::ZAck {
+        return Err(io::Error::new(ErrorKind::Other, "zmodem: file not accepted"));
+    }
+
+    send_hex_header(port, FrameType::ZData, 0)?;
+    let chunk_count = data.chunks(SUBPACKET_LEN).count();
+    for (offset, chunk) in data.chunks(SUBPACKET_LEN).enumerate() {
+        let at_end = offset + 1 == chunk_count;
+        let end = if at_end { ZCRCE } else { ZCRCW };
+        send_data_subpacket(port, chunk, end)?;
+
+        if !at_end {
+            let (frame, _) = recv_hex_header(port, Duration::from_secs(10))?;
+            if frame != FrameType::ZAck {
+                return Err(io::Error::new(ErrorKind::Other, "zmodem: expected ZACK"));
+            }
+        }
+    }
+
+    send_hex_header(port, FrameType::ZEof, data.len() as u32)?;
+    let (frame, _) = recv_hex_header(port, Duration::from_secs(10))?;
+    if frame != FrameType::ZRinit {
+        log::debug!("zmodem: unexpected post-ZEOF frame {frame:?}");
+    }
+
+    send_hex_header(port, FrameType::ZFin, 0)?;
+    log::debug!("zmodem: sent {} ({} bytes)", name, data.len());
+    Ok(())
+}
+
+/// Drive the receiver side of a ZMODEM transfer: announce readiness with
+/// `ZRINIT`, accept the incoming `ZFILE`, then collect `ZDATA` subpackets
+/// into `download_dir`.
+pub fn receive_file<S: Read + Write>(port: &mut S, download_dir: &Path) -> io::Result<()> {
+    send_hex_header(port, FrameType::ZRinit, 0)?;
+
+    let (frame, _) = recv_hex_header(port, Duration::from_secs(30))?;
+    if frame != FrameType::ZFile {
+        return Err(io::Error::new(ErrorKind::Other, "zmodem: expected ZFILE"));
+    }
+    let (subheader, _) = recv_data_subpacket(port, Duration::from_secs(10))?;
+    let text = String::from_utf8_lossy(&subheader);
+    let name = text.split('\0').next().unwrap_or("zmodem.bin").to_string();
+
+    send_hex_header(port, FrameType::ZRpos, 0)?;
+
+    let (frame, _) = recv_hex_header(port, Duration::from_secs(10))?;
+    if frame != FrameType::ZData {
+        return Err(io::Error::new(ErrorKind::Other, "zmodem: expected ZDATA"));
+    }
+
+    let mut received = Vec::new();
+    loop {
+        let (chunk, end) = recv_data_subpacket(port, Duration::from_secs(10))?;
+        received.extend_from_slice(&chunk);
+        match end {
+            ZCRCW => send_hex_header(port, FrameType::ZAck, received.len() as u32)?,
+            ZCRCE => break,
+            _ => {}
+        }
+    }
+
+    let (frame, _) = recv_hex_header(port, Duration::from_secs(10))?;
+    if frame != FrameType::ZEof {
+        log::debug!("zmodem: unexpected frame after data: {frame:?}");
+    }
+    send_hex_header(port, FrameType::ZRinit, 0)?;
+
+    let (frame, _) = recv_hex_header(port, Duration::from_secs(10))?;
+    if frame == FrameType::ZFin {
+        send_hex_header(port, FrameType::ZFin, 0)?;
+    }
+
+    // The name came from the remote peer's ZFILE subpacket verbatim; strip it
+    // to its final path component so an absolute path or `../` traversal
+    // can't write outside `download_dir`.
+    let name = Path::new(&name)
+        .file_name()
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "zmodem: bad file name"))?;
+
+    fs::create_dir_all(download_dir)?;
+    fs::write(download_dir.join(name), &received)?;
+    log::debug!("zmodem: received {:?} ({} bytes)", name, received.len());
+    Ok(())
+}